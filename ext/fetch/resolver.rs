@@ -6,7 +6,9 @@ use std::net::{
 };
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::task::{self, Poll};
+use std::time::Duration;
 use std::{fmt, io, vec};
 
 use hyper_util::client::legacy::connect::dns::Name;
@@ -14,21 +16,239 @@ use tokio::task::JoinHandle;
 use tower_http::decompression::DecompressionBody;
 use tower_service::Service;
 
+mod cache;
+mod dns;
+mod happy_eyeballs;
+
+pub(super) use cache::CacheConfig;
+pub(super) use cache::CacheStats;
+pub(super) use cache::CacheStatsSnapshot;
+// `ResolverBackend` below is `pub` and exposes `HickoryResolverConfig` in a
+// variant, so these three have to be re-exported at least as widely or we'd
+// hit `private_interfaces`.
+pub use dns::DnsTransport;
+pub use dns::HickoryResolverConfig;
+pub use dns::LookupIpStrategy;
+pub(super) use dns::HickoryResolver;
+
+/// A backend that resolves a [`Name`] into a set of [`SocketAddrs`].
+/// `CustomResolver` delegates to one of these rather than hardcoding a
+/// single resolution strategy, so the transport (blocking `getaddrinfo`,
+/// fully-async `hickory-resolver`, ...) can be swapped, and a caching
+/// layer can sit in front of it, without touching the `Service<Name>`
+/// plumbing.
+pub(super) trait DnsResolver: Send + Sync {
+  fn lookup(&self, name: Name) -> LookupFuture;
+}
+
+/// The result of a single backend lookup.
+pub(super) struct Lookup {
+  pub(super) addrs: SocketAddrs,
+  /// The TTL the backend reported for this lookup, when it exposes one
+  /// (e.g. `hickory`'s record TTLs). `None` for backends like blocking
+  /// `getaddrinfo` that don't expose per-record TTLs.
+  pub(super) ttl: Option<Duration>,
+}
+
+pub(super) type LookupFuture =
+  Pin<Box<dyn Future<Output = Result<Lookup, io::Error>> + Send>>;
+
 /// A resolver using blocking `getaddrinfo` calls in a threadpool.
+#[derive(Default)]
+struct GaiResolver;
+
+/// A future resolving a name via blocking `getaddrinfo`.
+struct GaiLookupFuture {
+  inner: JoinHandle<Result<SocketAddrs, io::Error>>,
+}
+
+impl DnsResolver for GaiResolver {
+  fn lookup(&self, name: Name) -> LookupFuture {
+    let blocking = tokio::task::spawn_blocking(move || {
+      // debug!("resolving host={:?}", name.host);
+      (name.as_str(), 0)
+        .to_socket_addrs()
+        .map(|i| SocketAddrs { iter: i })
+    });
+
+    Box::pin(async move {
+      let addrs = GaiLookupFuture { inner: blocking }.await?;
+      // getaddrinfo exposes no TTL; the caching layer falls back to its
+      // configured default.
+      Ok(Lookup { addrs, ttl: None })
+    })
+  }
+}
+
+impl Future for GaiLookupFuture {
+  type Output = Result<SocketAddrs, io::Error>;
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut task::Context<'_>,
+  ) -> Poll<Self::Output> {
+    Pin::new(&mut self.inner).poll(cx).map(|res| match res {
+      Ok(Ok(addrs)) => Ok(addrs),
+      Ok(Err(err)) => Err(err),
+      Err(join_err) => {
+        if join_err.is_cancelled() {
+          Err(io::Error::new(io::ErrorKind::Interrupted, join_err))
+        } else {
+          panic!("gai background task failed: {:?}", join_err)
+        }
+      }
+    })
+  }
+}
+
+impl Drop for GaiLookupFuture {
+  fn drop(&mut self) {
+    self.inner.abort();
+  }
+}
+
+/// Selects which [`DnsResolver`] backend a [`CustomResolver`] performs
+/// lookups through.
+pub enum ResolverBackend {
+  /// Blocking `getaddrinfo` calls on a threadpool. The default.
+  Gai,
+  /// Fully-async resolution via `hickory-resolver`, optionally over
+  /// DNS-over-TLS or DNS-over-HTTPS.
+  Hickory(HickoryResolverConfig),
+}
+
+impl Default for ResolverBackend {
+  fn default() -> Self {
+    ResolverBackend::Gai
+  }
+}
+
+impl ResolverBackend {
+  fn build(self) -> Arc<dyn DnsResolver> {
+    match self {
+      ResolverBackend::Gai => Arc::new(GaiResolver),
+      ResolverBackend::Hickory(config) => Arc::new(HickoryResolver::new(config)),
+    }
+  }
+}
+
+/// Builds a [`CustomResolver`] with a non-default backend, host
+/// overrides, and/or a caching layer in front of the backend.
+#[derive(Default)]
+pub struct CustomResolverBuilder {
+  backend: ResolverBackend,
+  overrides: HashMap<String, Vec<SocketAddr>>,
+  cache: Option<CacheConfig>,
+}
+
+impl CustomResolverBuilder {
+  pub fn backend(mut self, backend: ResolverBackend) -> Self {
+    self.backend = backend;
+    self
+  }
+
+  /// Sets the host overrides. Keys may be an exact hostname, a wildcard
+  /// pattern (`*.internal.example.com`), or a bare suffix
+  /// (`.example.com`) redirecting an entire subtree; see
+  /// [`resolve_override`] for how they're matched. Keys are compared
+  /// case-insensitively, so two keys differing only by case collapse
+  /// into one entry, same as any other `HashMap` key collision.
+  pub fn overrides(mut self, overrides: HashMap<String, Vec<SocketAddr>>) -> Self {
+    self.overrides = overrides
+      .into_iter()
+      .map(|(pattern, addrs)| (pattern.to_ascii_lowercase(), addrs))
+      .collect();
+    self
+  }
+
+  /// Wraps the selected backend in a TTL-aware cache. Overrides still
+  /// short-circuit both the cache and the backend.
+  pub fn cache(mut self, config: CacheConfig) -> Self {
+    self.cache = Some(config);
+    self
+  }
+
+  pub fn build(self) -> CustomResolver {
+    let backend = self.backend.build();
+    let (backend, cache_stats) = match self.cache {
+      Some(config) => {
+        let caching = cache::CachingResolver::new(backend, config);
+        let stats = caching.stats();
+        (Arc::new(caching) as Arc<dyn DnsResolver>, Some(stats))
+      }
+      None => (backend, None),
+    };
+
+    CustomResolver {
+      backend,
+      overrides: self.overrides,
+      cache_stats,
+    }
+  }
+}
+
+/// Resolves `host` against the override table, case-insensitively.
+/// `overrides` keys are matched in order of specificity: an exact host
+/// match wins, then the longest matching wildcard (`*.sub.domain`)
+/// pattern, then the longest matching bare suffix (`.domain`) pattern.
+/// Returns `None` when nothing matches, so normal resolution proceeds.
+///
+/// Keys are assumed to already be lowercase (see
+/// [`CustomResolverBuilder::overrides`]); only `host` is lowercased here.
+fn resolve_override<'a>(
+  overrides: &'a HashMap<String, Vec<SocketAddr>>,
+  host: &str,
+) -> Option<&'a [SocketAddr]> {
+  let host = host.to_ascii_lowercase();
+
+  if let Some(addrs) = overrides.get(host.as_str()) {
+    return Some(addrs);
+  }
+
+  overrides
+    .iter()
+    .filter_map(|(pattern, addrs)| {
+      pattern_specificity(pattern, &host).map(|specificity| (specificity, addrs))
+    })
+    .max_by_key(|(specificity, _)| *specificity)
+    .map(|(_, addrs)| addrs.as_slice())
+}
+
+/// Returns a higher number for a more specific wildcard/suffix match, or
+/// `None` if `pattern` doesn't match `host`. Exact matches are handled by
+/// the caller; `pattern` is assumed lowercase already.
+fn pattern_specificity(pattern: &str, host: &str) -> Option<usize> {
+  if let Some(suffix) = pattern.strip_prefix("*.") {
+    // A wildcard matches exactly one additional label, like a TLS SAN
+    // wildcard: `*.sub.domain` matches `api.sub.domain` but not
+    // `tenant.api.sub.domain` (use a bare `.sub.domain` suffix for that).
+    let label = host.strip_suffix(suffix)?.strip_suffix('.')?;
+    (!label.is_empty() && !label.contains('.')).then(|| pattern.len())
+  } else if pattern.starts_with('.') {
+    (host.len() > pattern.len() && host.ends_with(pattern)).then(|| pattern.len())
+  } else {
+    None
+  }
+}
+
+/// A resolver delegating lookups to a pluggable [`DnsResolver`] backend,
+/// with a short-circuit for host overrides installed via
+/// [`CustomResolver::with_overrides`].
 #[derive(Clone)]
 pub struct CustomResolver {
-  _priv: (),
+  backend: Arc<dyn DnsResolver>,
   overrides: HashMap<String, Vec<SocketAddr>>,
+  cache_stats: Option<CacheStats>,
 }
 
-/// An iterator of IP addresses returned from `getaddrinfo`.
+/// An iterator of IP addresses returned from the resolver backend.
 pub struct CustomAddrs {
   inner: SocketAddrs,
 }
 
 /// A future to resolve a name returned by `CustomResolver`.
 pub struct CustomResolverFuture {
-  inner: JoinHandle<Result<SocketAddrs, io::Error>>,
+  inner: LookupFuture,
 }
 
 /// Error indicating a given string was not a valid domain name.
@@ -44,19 +264,26 @@ impl fmt::Display for InvalidNameError {
 impl Error for InvalidNameError {}
 
 impl CustomResolver {
-  /// Construct a new `CustomResolver`.
+  /// Construct a new `CustomResolver` using the default `getaddrinfo`
+  /// backend and no overrides.
   pub fn new() -> Self {
-    CustomResolver {
-      _priv: (),
-      overrides: Default::default(),
-    }
+    CustomResolverBuilder::default().build()
   }
 
   pub fn with_overrides(overrides: HashMap<String, Vec<SocketAddr>>) -> Self {
-    Self {
-      _priv: (),
-      overrides,
-    }
+    CustomResolverBuilder::default().overrides(overrides).build()
+  }
+
+  /// Construct a `CustomResolver` with a non-default backend and/or
+  /// overrides, e.g. `CustomResolver::builder().backend(ResolverBackend::Hickory(config)).build()`.
+  pub fn builder() -> CustomResolverBuilder {
+    CustomResolverBuilder::default()
+  }
+
+  /// Returns a snapshot of this resolver's cache counters, if it was built
+  /// with [`CustomResolverBuilder::cache`].
+  pub fn cache_stats(&self) -> Option<CacheStatsSnapshot> {
+    self.cache_stats.as_ref().map(CacheStats::snapshot)
   }
 }
 
@@ -73,25 +300,16 @@ impl Service<Name> for CustomResolver {
   }
 
   fn call(&mut self, name: Name) -> Self::Future {
-    if let Some(addrs) = self.overrides.get(name.as_str()) {
-      let addrs = addrs.clone();
+    if let Some(addrs) = resolve_override(&self.overrides, name.as_str()) {
+      let addrs = SocketAddrs::new(addrs.to_vec());
       return CustomResolverFuture {
-        inner: tokio::spawn(async {
-          Ok(SocketAddrs {
-            iter: addrs.into_iter(),
-          })
-        }),
+        inner: Box::pin(async move { Ok(Lookup { addrs, ttl: None }) }),
       };
     }
 
-    let blocking = tokio::task::spawn_blocking(move || {
-      // debug!("resolving host={:?}", name.host);
-      (name.as_str(), 0)
-        .to_socket_addrs()
-        .map(|i| SocketAddrs { iter: i })
-    });
-
-    CustomResolverFuture { inner: blocking }
+    CustomResolverFuture {
+      inner: self.backend.lookup(name),
+    }
   }
 }
 
@@ -108,17 +326,11 @@ impl Future for CustomResolverFuture {
     mut self: Pin<&mut Self>,
     cx: &mut task::Context<'_>,
   ) -> Poll<Self::Output> {
-    Pin::new(&mut self.inner).poll(cx).map(|res| match res {
-      Ok(Ok(addrs)) => Ok(CustomAddrs { inner: addrs }),
-      Ok(Err(err)) => Err(err),
-      Err(join_err) => {
-        if join_err.is_cancelled() {
-          Err(io::Error::new(io::ErrorKind::Interrupted, join_err))
-        } else {
-          panic!("gai background task failed: {:?}", join_err)
-        }
-      }
-    })
+    self
+      .inner
+      .as_mut()
+      .poll(cx)
+      .map(|res| res.map(|lookup| CustomAddrs { inner: lookup.addrs }))
   }
 }
 
@@ -128,12 +340,6 @@ impl fmt::Debug for CustomResolverFuture {
   }
 }
 
-impl Drop for CustomResolverFuture {
-  fn drop(&mut self) {
-    self.inner.abort();
-  }
-}
-
 impl Iterator for CustomAddrs {
   type Item = SocketAddr;
 
@@ -148,18 +354,18 @@ impl fmt::Debug for CustomAddrs {
   }
 }
 
-pub(super) struct SocketAddrs {
+pub(crate) struct SocketAddrs {
   iter: vec::IntoIter<SocketAddr>,
 }
 
 impl SocketAddrs {
-  pub(super) fn new(addrs: Vec<SocketAddr>) -> Self {
+  pub(crate) fn new(addrs: Vec<SocketAddr>) -> Self {
     SocketAddrs {
       iter: addrs.into_iter(),
     }
   }
 
-  pub(super) fn try_parse(host: &str, port: u16) -> Option<SocketAddrs> {
+  pub(crate) fn try_parse(host: &str, port: u16) -> Option<SocketAddrs> {
     if let Ok(addr) = host.parse::<Ipv4Addr>() {
       let addr = SocketAddrV4::new(addr, port);
       return Some(SocketAddrs {
@@ -180,7 +386,7 @@ impl SocketAddrs {
     SocketAddrs::new(self.iter.filter(predicate).collect())
   }
 
-  pub(super) fn split_by_preference(
+  pub(crate) fn split_by_preference(
     self,
     local_addr_ipv4: Option<Ipv4Addr>,
     local_addr_ipv6: Option<Ipv6Addr>,
@@ -281,6 +487,57 @@ mod tests {
     assert!(fallback.is_empty());
   }
 
+  #[test]
+  fn test_builder_defaults_to_gai_backend() {
+    let builder = CustomResolver::builder();
+    assert!(matches!(builder.backend, ResolverBackend::Gai));
+    let resolver = builder.build();
+    assert!(resolver.overrides.is_empty());
+  }
+
+  #[test]
+  fn test_resolve_override_prefers_more_specific_matches() {
+    let pinned: SocketAddr = (Ipv4Addr::new(10, 0, 0, 1), 80).into();
+    let wildcard: SocketAddr = (Ipv4Addr::new(10, 0, 0, 2), 80).into();
+    let suffix: SocketAddr = (Ipv4Addr::new(10, 0, 0, 3), 80).into();
+
+    let overrides = HashMap::from([
+      ("api.internal.example.com".to_string(), vec![pinned]),
+      ("*.internal.example.com".to_string(), vec![wildcard]),
+      (".example.com".to_string(), vec![suffix]),
+    ]);
+
+    // Exact match wins over both wildcard and suffix.
+    assert_eq!(
+      resolve_override(&overrides, "api.internal.example.com"),
+      Some(&[pinned][..])
+    );
+    // Wildcard wins over the broader bare suffix.
+    assert_eq!(
+      resolve_override(&overrides, "other.internal.example.com"),
+      Some(&[wildcard][..])
+    );
+    // Falls through to the bare suffix for the rest of the subtree.
+    assert_eq!(
+      resolve_override(&overrides, "unrelated.example.com"),
+      Some(&[suffix][..])
+    );
+    // A wildcard only matches one additional label, unlike a bare suffix.
+    assert_eq!(
+      resolve_override(&overrides, "tenant.other.internal.example.com"),
+      Some(&[suffix][..])
+    );
+    // A bare suffix doesn't match the domain itself, only its subtree.
+    assert_eq!(resolve_override(&overrides, "example.com"), None);
+    // Matching is case-insensitive.
+    assert_eq!(
+      resolve_override(&overrides, "API.INTERNAL.EXAMPLE.COM"),
+      Some(&[pinned][..])
+    );
+    // Unrelated hosts don't match at all.
+    assert_eq!(resolve_override(&overrides, "example.org"), None);
+  }
+
   #[test]
   fn test_name_from_str() {
     const DOMAIN: &str = "test.example.com";
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+use std::{fmt, io};
+
+use futures_util::future::Shared;
+use futures_util::FutureExt;
+use hyper_util::client::legacy::connect::dns::Name;
+use lru::LruCache;
+
+use super::DnsResolver;
+use super::Lookup;
+use super::LookupFuture;
+use super::SocketAddrs;
+
+/// Configuration for the [`CachingResolver`] decorator.
+#[derive(Clone, Debug)]
+pub struct CacheConfig {
+  /// Maximum number of distinct names held in the cache at once.
+  pub capacity: NonZeroUsize,
+  /// TTL used when the backend doesn't report one (e.g. blocking
+  /// `getaddrinfo`, which exposes no per-record TTL).
+  pub default_ttl: Duration,
+  /// How long a failed or empty lookup is cached, so a storm of requests
+  /// for a dead host doesn't each pay full resolution cost.
+  pub negative_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+  fn default() -> Self {
+    Self {
+      capacity: NonZeroUsize::new(256).unwrap(),
+      default_ttl: Duration::from_secs(30),
+      negative_ttl: Duration::from_secs(5),
+    }
+  }
+}
+
+/// Point-in-time hit/miss/eviction counters for a [`CachingResolver`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStatsSnapshot {
+  pub hits: u64,
+  pub misses: u64,
+  pub evictions: u64,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+  hits: AtomicU64,
+  misses: AtomicU64,
+  evictions: AtomicU64,
+}
+
+/// A handle to a [`CachingResolver`]'s counters, cheap to clone and safe to
+/// read concurrently with lookups.
+#[derive(Clone)]
+pub struct CacheStats {
+  counters: Arc<CacheCounters>,
+}
+
+impl CacheStats {
+  pub fn snapshot(&self) -> CacheStatsSnapshot {
+    CacheStatsSnapshot {
+      hits: self.counters.hits.load(Ordering::Relaxed),
+      misses: self.counters.misses.load(Ordering::Relaxed),
+      evictions: self.counters.evictions.load(Ordering::Relaxed),
+    }
+  }
+}
+
+/// A cached result: either the resolved addresses or the (negatively
+/// cached) failure of the last lookup.
+type CachedResult = Result<Vec<SocketAddr>, Arc<io::Error>>;
+
+struct CacheEntry {
+  value: CachedResult,
+  expires_at: Instant,
+}
+
+/// A lookup shared by every caller coalesced onto the same in-flight
+/// request for a name.
+type SharedLookup = Shared<Pin<Box<dyn Future<Output = CachedResult> + Send>>>;
+
+struct State {
+  entries: LruCache<String, CacheEntry>,
+  in_flight: HashMap<String, SharedLookup>,
+}
+
+struct Inner {
+  backend: Arc<dyn DnsResolver>,
+  config: CacheConfig,
+  state: Mutex<State>,
+  stats: CacheStats,
+}
+
+impl Inner {
+  fn fresh_hit(&self, key: &str) -> Option<CachedResult> {
+    let mut state = self.state.lock().unwrap();
+    match state.entries.get(key) {
+      Some(entry) if entry.expires_at > Instant::now() => {
+        Some(entry.value.clone())
+      }
+      Some(_) => {
+        state.entries.pop(key);
+        None
+      }
+      None => None,
+    }
+  }
+
+  fn store(&self, key: String, value: CachedResult, ttl: Option<Duration>) {
+    let ttl = match &value {
+      Ok(addrs) if addrs.is_empty() => self.config.negative_ttl,
+      Ok(_) => ttl.unwrap_or(self.config.default_ttl),
+      Err(_) => self.config.negative_ttl,
+    };
+
+    let mut state = self.state.lock().unwrap();
+    state.in_flight.remove(&key);
+    let evicted = state.entries.push(
+      key,
+      CacheEntry {
+        value,
+        expires_at: Instant::now() + ttl,
+      },
+    );
+    if evicted.is_some() {
+      self.stats.counters.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+}
+
+/// A TTL-aware [`DnsResolver`] decorator: caches resolved addresses keyed
+/// by name, honoring the backend's reported TTL (falling back to a
+/// configured default when the backend doesn't expose one), negatively
+/// caches failed or empty lookups for a short, separately-configurable
+/// duration, evicts least-recently-used entries once over capacity, and
+/// coalesces concurrent lookups for the same in-flight name onto a single
+/// backend request.
+pub(super) struct CachingResolver {
+  inner: Arc<Inner>,
+}
+
+impl CachingResolver {
+  pub(super) fn new(
+    backend: Arc<dyn DnsResolver>,
+    config: CacheConfig,
+  ) -> Self {
+    Self {
+      inner: Arc::new(Inner {
+        backend,
+        state: Mutex::new(State {
+          entries: LruCache::new(config.capacity),
+          in_flight: HashMap::new(),
+        }),
+        config,
+        stats: CacheStats {
+          counters: Arc::new(CacheCounters::default()),
+        },
+      }),
+    }
+  }
+
+  pub(super) fn stats(&self) -> CacheStats {
+    self.inner.stats.clone()
+  }
+}
+
+impl DnsResolver for CachingResolver {
+  fn lookup(&self, name: Name) -> LookupFuture {
+    let key = name.as_str().to_ascii_lowercase();
+
+    if let Some(value) = self.inner.fresh_hit(&key) {
+      self.inner.stats.counters.hits.fetch_add(1, Ordering::Relaxed);
+      return Box::pin(async move { to_lookup(value) });
+    }
+
+    self.inner.stats.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+    let shared = {
+      let mut state = self.inner.state.lock().unwrap();
+      match state.in_flight.get(&key) {
+        Some(shared) => shared.clone(),
+        None => {
+          let inner = self.inner.clone();
+          let fetch_key = key.clone();
+          let fetch: Pin<Box<dyn Future<Output = CachedResult> + Send>> =
+            Box::pin(async move {
+              let result = inner.backend.lookup(name).await;
+              let (value, ttl) = match result {
+                Ok(lookup) => (Ok(lookup.addrs.collect()), lookup.ttl),
+                Err(err) => (Err(Arc::new(err)), None),
+              };
+              inner.store(fetch_key, value.clone(), ttl);
+              value
+            });
+          let shared = fetch.shared();
+          state.in_flight.insert(key, shared.clone());
+          shared
+        }
+      }
+    };
+
+    Box::pin(async move { to_lookup(shared.await) })
+  }
+}
+
+fn to_lookup(value: CachedResult) -> Result<Lookup, io::Error> {
+  match value {
+    Ok(addrs) => Ok(Lookup {
+      addrs: SocketAddrs::new(addrs),
+      ttl: None,
+    }),
+    Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+  }
+}
+
+impl fmt::Debug for CachingResolver {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.pad("CachingResolver")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::Ipv4Addr;
+  use std::str::FromStr;
+  use std::sync::atomic::AtomicUsize;
+
+  use super::*;
+
+  struct CountingResolver {
+    calls: AtomicUsize,
+    fail: bool,
+    empty: bool,
+  }
+
+  impl DnsResolver for CountingResolver {
+    fn lookup(&self, _name: Name) -> LookupFuture {
+      self.calls.fetch_add(1, Ordering::SeqCst);
+      let fail = self.fail;
+      let empty = self.empty;
+      Box::pin(async move {
+        if fail {
+          Err(io::Error::new(io::ErrorKind::NotFound, "no such host"))
+        } else if empty {
+          Ok(Lookup {
+            addrs: SocketAddrs::new(vec![]),
+            ttl: Some(Duration::from_secs(60)),
+          })
+        } else {
+          Ok(Lookup {
+            addrs: SocketAddrs::new(vec![SocketAddr::from((
+              Ipv4Addr::new(127, 0, 0, 1),
+              80,
+            ))]),
+            ttl: Some(Duration::from_secs(60)),
+          })
+        }
+      })
+    }
+  }
+
+  #[tokio::test]
+  async fn caches_successful_lookups() {
+    let backend = Arc::new(CountingResolver {
+      calls: AtomicUsize::new(0),
+      fail: false,
+      empty: false,
+    });
+    let resolver = CachingResolver::new(backend.clone(), CacheConfig::default());
+
+    let name = Name::from_str("example.com").unwrap();
+    resolver.lookup(name.clone()).await.unwrap();
+    resolver.lookup(name).await.unwrap();
+
+    assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(resolver.stats().snapshot().hits, 1);
+    assert_eq!(resolver.stats().snapshot().misses, 1);
+  }
+
+  #[tokio::test]
+  async fn negatively_caches_failed_lookups() {
+    let backend = Arc::new(CountingResolver {
+      calls: AtomicUsize::new(0),
+      fail: true,
+      empty: false,
+    });
+    let resolver = CachingResolver::new(backend.clone(), CacheConfig::default());
+
+    let name = Name::from_str("dead.example.com").unwrap();
+    assert!(resolver.lookup(name.clone()).await.is_err());
+    assert!(resolver.lookup(name).await.is_err());
+
+    assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn negatively_caches_empty_lookups() {
+    let backend = Arc::new(CountingResolver {
+      calls: AtomicUsize::new(0),
+      fail: false,
+      empty: true,
+    });
+    let resolver = CachingResolver::new(backend.clone(), CacheConfig::default());
+
+    let name = Name::from_str("empty.example.com").unwrap();
+    let first = resolver.lookup(name.clone()).await.unwrap();
+    let second = resolver.lookup(name).await.unwrap();
+
+    assert!(first.addrs.is_empty());
+    assert!(second.addrs.is_empty());
+    assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+  }
+
+  #[tokio::test]
+  async fn coalesces_concurrent_lookups() {
+    let backend = Arc::new(CountingResolver {
+      calls: AtomicUsize::new(0),
+      fail: false,
+      empty: false,
+    });
+    let resolver =
+      Arc::new(CachingResolver::new(backend.clone(), CacheConfig::default()));
+
+    let name = Name::from_str("example.com").unwrap();
+    let (a, b) = tokio::join!(
+      resolver.lookup(name.clone()),
+      resolver.lookup(name.clone())
+    );
+    assert!(a.is_ok());
+    assert!(b.is_ok());
+
+    assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+  }
+}
@@ -0,0 +1,167 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use hickory_resolver::config::NameServerConfig;
+use hickory_resolver::config::Protocol;
+use hickory_resolver::config::ResolverConfig;
+use hickory_resolver::config::ResolverOpts;
+use hickory_resolver::system_conf;
+use hickory_resolver::TokioAsyncResolver;
+use hyper_util::client::legacy::connect::dns::Name;
+
+pub use hickory_resolver::config::LookupIpStrategy;
+
+use super::DnsResolver;
+use super::Lookup;
+use super::LookupFuture;
+use super::SocketAddrs;
+
+/// How DNS queries are transported to the configured nameservers.
+#[derive(Clone, Debug)]
+pub enum DnsTransport {
+  /// Plain UDP, falling back to TCP for truncated responses.
+  Udp,
+  /// DNS-over-TLS (RFC 7858), authenticated against `server_name`.
+  Tls { server_name: String },
+  /// DNS-over-HTTPS (RFC 8484), authenticated against `server_name`.
+  Https { server_name: String },
+}
+
+/// Configuration for the fully-async [`HickoryResolver`] backend.
+#[derive(Clone, Debug)]
+pub struct HickoryResolverConfig {
+  /// Upstream nameservers to query. When empty, the system config (e.g.
+  /// `/etc/resolv.conf`) is read via `hickory_resolver::system_conf`,
+  /// matching what blocking `getaddrinfo` would have used — we never
+  /// silently switch a caller's lookups to a third-party resolver.
+  pub nameservers: Vec<SocketAddr>,
+  pub strategy: LookupIpStrategy,
+  pub transport: DnsTransport,
+}
+
+impl Default for HickoryResolverConfig {
+  fn default() -> Self {
+    Self {
+      nameservers: Vec::new(),
+      strategy: LookupIpStrategy::Ipv4AndIpv6,
+      transport: DnsTransport::Udp,
+    }
+  }
+}
+
+/// A [`DnsResolver`] backend that performs lookups fully asynchronously via
+/// `hickory-resolver`, rather than blocking `getaddrinfo` calls on a
+/// thread pool. Supports custom upstream nameservers and encrypted
+/// transports (DoT/DoH) for networks where the system stub resolver can't
+/// be trusted.
+#[derive(Clone)]
+pub(super) struct HickoryResolver {
+  inner: Arc<TokioAsyncResolver>,
+}
+
+impl HickoryResolver {
+  pub(super) fn new(config: HickoryResolverConfig) -> Self {
+    let mut opts = ResolverOpts::default();
+    opts.ip_strategy = config.strategy;
+
+    let resolver_config = if config.nameservers.is_empty() {
+      // Mirror what `getaddrinfo` would have used: the OS-configured
+      // resolver, not hickory's built-in (Google) default. If the system
+      // config can't be read, no nameservers are configured at all.
+      let system_config = system_conf::read_system_conf()
+        .map(|(resolver_config, _opts)| resolver_config)
+        .unwrap_or_else(|_| ResolverConfig::new());
+      match &config.transport {
+        // Plain UDP can use the system config as-is.
+        DnsTransport::Udp => system_config,
+        // An encrypted transport was requested but no nameservers were
+        // given to speak it to; apply it to the system-derived servers,
+        // rewriting their port to the encrypted transport's well-known
+        // port (the system config's :53 is only ever a plaintext stub).
+        transport => build_resolver_config(
+          &system_config
+            .name_servers()
+            .iter()
+            .map(|ns| SocketAddr::new(ns.socket_addr.ip(), transport_port(transport)))
+            .collect::<Vec<_>>(),
+          &config.transport,
+        ),
+      }
+    } else {
+      build_resolver_config(&config.nameservers, &config.transport)
+    };
+
+    HickoryResolver {
+      inner: Arc::new(TokioAsyncResolver::tokio(resolver_config, opts)),
+    }
+  }
+}
+
+/// The well-known port for `transport`, used when we only have a plaintext
+/// (`:53`) nameserver address to start from, e.g. one read from the
+/// system's `/etc/resolv.conf`.
+fn transport_port(transport: &DnsTransport) -> u16 {
+  match transport {
+    DnsTransport::Udp => 53,
+    DnsTransport::Tls { .. } => 853,
+    DnsTransport::Https { .. } => 443,
+  }
+}
+
+fn build_resolver_config(
+  nameservers: &[SocketAddr],
+  transport: &DnsTransport,
+) -> ResolverConfig {
+  let (protocol, tls_dns_name) = match transport {
+    DnsTransport::Udp => (Protocol::Udp, None),
+    DnsTransport::Tls { server_name } => (Protocol::Tls, Some(server_name.clone())),
+    DnsTransport::Https { server_name } => {
+      (Protocol::Https, Some(server_name.clone()))
+    }
+  };
+
+  let mut resolver_config = ResolverConfig::new();
+  for socket_addr in nameservers {
+    resolver_config.add_name_server(NameServerConfig {
+      socket_addr: *socket_addr,
+      protocol,
+      tls_dns_name: tls_dns_name.clone(),
+      trust_negative_responses: true,
+      bind_addr: None,
+    });
+    // hickory only retries a truncated UDP response over TCP when a TCP
+    // entry exists for the same server.
+    if let DnsTransport::Udp = transport {
+      resolver_config.add_name_server(NameServerConfig {
+        socket_addr: *socket_addr,
+        protocol: Protocol::Tcp,
+        tls_dns_name: None,
+        trust_negative_responses: true,
+        bind_addr: None,
+      });
+    }
+  }
+  resolver_config
+}
+
+impl DnsResolver for HickoryResolver {
+  fn lookup(&self, name: Name) -> LookupFuture {
+    let resolver = self.inner.clone();
+    Box::pin(async move {
+      let host = name.as_str().trim_end_matches('.').to_owned();
+      let lookup = resolver
+        .lookup_ip(host)
+        .await
+        .map_err(io::Error::other)?;
+      let ttl = lookup.valid_until().checked_duration_since(Instant::now());
+      let addrs =
+        lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect::<Vec<_>>();
+      Ok(Lookup {
+        addrs: SocketAddrs::new(addrs),
+        ttl,
+      })
+    })
+  }
+}
@@ -0,0 +1,284 @@
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+use super::SocketAddrs;
+
+/// The RFC 8305 "Connection Attempt Delay": how long we wait after starting
+/// a connection attempt before racing the next address concurrently.
+const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+const MIN_ATTEMPT_DELAY: Duration = Duration::from_millis(100);
+const MAX_ATTEMPT_DELAY: Duration = Duration::from_secs(2);
+
+/// Races TCP connection attempts across an interleaved preferred/fallback
+/// address list, per RFC 8305 ("Happy Eyeballs v2"). A dead address in one
+/// family no longer stalls every other address behind a full connect
+/// timeout: once the attempt delay elapses (or an attempt fails), the next
+/// address is tried concurrently and the first attempt to succeed wins.
+#[derive(Clone)]
+pub(crate) struct HappyEyeballsConnector {
+  attempt_delay: Duration,
+  connect_timeout: Option<Duration>,
+}
+
+impl Default for HappyEyeballsConnector {
+  fn default() -> Self {
+    Self {
+      attempt_delay: DEFAULT_ATTEMPT_DELAY,
+      connect_timeout: None,
+    }
+  }
+}
+
+impl HappyEyeballsConnector {
+  pub(crate) fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the Connection Attempt Delay, clamped to the RFC 8305 recommended
+  /// range of 100ms-2s.
+  pub(crate) fn with_attempt_delay(mut self, delay: Duration) -> Self {
+    self.attempt_delay = delay.clamp(MIN_ATTEMPT_DELAY, MAX_ATTEMPT_DELAY);
+    self
+  }
+
+  /// Sets an overall deadline for the whole race, after which it fails with
+  /// `io::ErrorKind::TimedOut` regardless of how many attempts are still
+  /// in flight.
+  pub(crate) fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+    self.connect_timeout = Some(timeout);
+    self
+  }
+
+  /// Interleaves the preferred and fallback address lists so families
+  /// alternate: preferred, fallback, preferred, fallback, ...
+  fn interleave(
+    mut preferred: SocketAddrs,
+    mut fallback: SocketAddrs,
+  ) -> Vec<SocketAddr> {
+    let mut addrs = Vec::with_capacity(preferred.len() + fallback.len());
+    loop {
+      let mut progressed = false;
+      if let Some(addr) = preferred.next() {
+        addrs.push(addr);
+        progressed = true;
+      }
+      if let Some(addr) = fallback.next() {
+        addrs.push(addr);
+        progressed = true;
+      }
+      if !progressed {
+        break;
+      }
+    }
+    addrs
+  }
+
+  /// Races `connect` across `preferred` and `fallback`, returning the first
+  /// successful connection. All other in-flight attempts are aborted once
+  /// a winner is found or every address has been exhausted.
+  pub(crate) async fn connect<C, Fut, T>(
+    &self,
+    preferred: SocketAddrs,
+    fallback: SocketAddrs,
+    connect: C,
+  ) -> io::Result<T>
+  where
+    C: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>> + Send + 'static,
+    T: Send + 'static,
+  {
+    let race = self.race(preferred, fallback, connect);
+    match self.connect_timeout {
+      Some(timeout) => tokio::time::timeout(timeout, race).await.unwrap_or_else(
+        |_| {
+          Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "happy eyeballs connect deadline exceeded",
+          ))
+        },
+      ),
+      None => race.await,
+    }
+  }
+
+  async fn race<C, Fut, T>(
+    &self,
+    preferred: SocketAddrs,
+    fallback: SocketAddrs,
+    connect: C,
+  ) -> io::Result<T>
+  where
+    C: Fn(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>> + Send + 'static,
+    T: Send + 'static,
+  {
+    let mut addrs = Self::interleave(preferred, fallback).into_iter();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut attempts = InFlightAttempts::default();
+    let mut pending = 0usize;
+
+    let Some(first) = addrs.next() else {
+      return Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no addresses to connect to",
+      ));
+    };
+    attempts.push(spawn_attempt(first, &connect, tx.clone()));
+    pending += 1;
+
+    let mut last_err = None;
+    loop {
+      tokio::select! {
+        Some(outcome) = rx.recv() => {
+          pending -= 1;
+          match outcome {
+            Ok(stream) => return Ok(stream),
+            Err(err) => {
+              last_err = Some(err);
+              if let Some(addr) = addrs.next() {
+                attempts.push(spawn_attempt(addr, &connect, tx.clone()));
+                pending += 1;
+              } else if pending == 0 {
+                return Err(last_err.take().unwrap());
+              }
+            }
+          }
+        }
+        _ = sleep(self.attempt_delay) => {
+          if let Some(addr) = addrs.next() {
+            attempts.push(spawn_attempt(addr, &connect, tx.clone()));
+            pending += 1;
+          }
+        }
+      }
+    }
+  }
+}
+
+fn spawn_attempt<C, Fut, T>(
+  addr: SocketAddr,
+  connect: &C,
+  tx: mpsc::UnboundedSender<io::Result<T>>,
+) -> JoinHandle<()>
+where
+  C: Fn(SocketAddr) -> Fut,
+  Fut: Future<Output = io::Result<T>> + Send + 'static,
+  T: Send + 'static,
+{
+  let fut = connect(addr);
+  tokio::spawn(async move {
+    let _ = tx.send(fut.await);
+  })
+}
+
+/// Aborts every outstanding attempt when dropped, the same cleanup
+/// `GaiLookupFuture` does for its own background task.
+#[derive(Default)]
+struct InFlightAttempts(Vec<JoinHandle<()>>);
+
+impl InFlightAttempts {
+  fn push(&mut self, handle: JoinHandle<()>) {
+    self.0.push(handle);
+  }
+}
+
+impl Drop for InFlightAttempts {
+  fn drop(&mut self) {
+    for handle in &self.0 {
+      handle.abort();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::net::Ipv4Addr;
+  use std::net::Ipv6Addr;
+  use std::sync::atomic::AtomicBool;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+  use std::sync::Arc;
+
+  fn addr(octet: u8, port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::new(127, 0, 0, octet), port))
+  }
+
+  fn v6addr(segment: u16, port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, segment), port))
+  }
+
+  #[test]
+  fn attempt_delay_is_clamped() {
+    let connector =
+      HappyEyeballsConnector::new().with_attempt_delay(Duration::from_millis(1));
+    assert_eq!(connector.attempt_delay, MIN_ATTEMPT_DELAY);
+
+    let connector = HappyEyeballsConnector::new()
+      .with_attempt_delay(Duration::from_secs(10));
+    assert_eq!(connector.attempt_delay, MAX_ATTEMPT_DELAY);
+  }
+
+  #[test]
+  fn interleave_alternates_families() {
+    let preferred = SocketAddrs::new(vec![v6addr(1, 80), v6addr(2, 80)]);
+    let fallback = SocketAddrs::new(vec![addr(1, 80)]);
+    let addrs = HappyEyeballsConnector::interleave(preferred, fallback);
+    assert_eq!(addrs, vec![v6addr(1, 80), addr(1, 80), v6addr(2, 80)]);
+  }
+
+  #[tokio::test]
+  async fn connects_to_first_success_and_aborts_the_rest() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    // Set only if the slow fallback attempt is allowed to run to
+    // completion instead of being aborted once addr(2) wins.
+    let slow_completed = Arc::new(AtomicBool::new(false));
+    let preferred = SocketAddrs::new(vec![addr(1, 80), addr(2, 80)]);
+    let fallback = SocketAddrs::new(vec![addr(3, 80)]);
+
+    let connector = HappyEyeballsConnector::new()
+      .with_attempt_delay(MIN_ATTEMPT_DELAY);
+    let attempts_clone = attempts.clone();
+    let slow_completed_clone = slow_completed.clone();
+    let result = connector
+      .connect(preferred, fallback, move |target| {
+        let attempts = attempts_clone.clone();
+        let slow_completed = slow_completed_clone.clone();
+        async move {
+          attempts.fetch_add(1, Ordering::SeqCst);
+          if target == addr(1, 80) {
+            Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+          } else if target == addr(3, 80) {
+            // Raced in once addr(1) fails, then outlived by addr(2);
+            // should get aborted before this sleep ever returns.
+            sleep(Duration::from_millis(500)).await;
+            slow_completed.store(true, Ordering::SeqCst);
+            Ok(target)
+          } else {
+            Ok(target)
+          }
+        }
+      })
+      .await
+      .unwrap();
+
+    assert_eq!(result, addr(2, 80));
+    // addr(1) fails immediately, which races in addr(3); the attempt
+    // delay then races in addr(2), which wins before addr(3) responds.
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+    // Give the losing addr(3) attempt enough time to have finished its
+    // sleep and flipped the flag if `connect` hadn't actually aborted it.
+    sleep(Duration::from_millis(600)).await;
+    assert!(
+      !slow_completed.load(Ordering::SeqCst),
+      "losing attempt should have been aborted, not left to run to completion"
+    );
+  }
+}